@@ -0,0 +1,34 @@
+//! Error types for this crate, defined using the `error-chain` crate.
+
+use std::path::PathBuf;
+
+error_chain! {
+    errors {
+        /// We could not read a file from disk.
+        ReadFile(path: PathBuf) {
+            description("error reading file")
+            display("error reading file {:?}", path)
+        }
+
+        /// We could not parse a line of an `env_file:`.
+        ParseEnv(line: String) {
+            description("error parsing env file")
+            display("cannot parse env file line: {:?}", line)
+        }
+
+        /// A string contained a `$` that did not form a valid variable
+        /// interpolation.  We refuse to pass these through silently so that
+        /// typos don't end up in the rendered output.
+        InterpolateInvalidSyntax(input: String) {
+            description("invalid variable interpolation syntax")
+            display("invalid variable interpolation syntax in {:?}", input)
+        }
+
+        /// A `${NAME:?msg}` or `${NAME?msg}` reference required a variable
+        /// that was unset (or empty).  We carry the supplied message.
+        InterpolateUndefined(name: String, msg: String) {
+            description("required variable is unset")
+            display("variable {:?} is required but unset: {}", name, msg)
+        }
+    }
+}
@@ -2,6 +2,41 @@
 // possibly after build-time preprocessing.  See v2.rs for an explanation
 // of how this works.
 
+/// Split a colon-delimited value such as `name:alias` or
+/// `host:container:mode` into its components, validating that the number of
+/// components falls within `[min, max]` and that none of them are empty (an
+/// empty component would round-trip ambiguously).  `kind` names the value
+/// for error messages.
+///
+/// This is the shared substrate for the several closely-related
+/// colon-separated compose grammars (aliased names, volume mounts, published
+/// ports, external links), so each can be expressed as a pair of arity bounds
+/// instead of re-implementing the parse/validate/serialize dance.
+fn split_colon_components(s: &str, kind: &'static str, min: usize, max: usize)
+    -> Result<Vec<String>, InvalidValueError>
+{
+    let components: Vec<&str> = s.split(':').collect();
+    if components.len() < min || components.len() > max
+        || components.iter().any(|c| c.is_empty())
+    {
+        return Err(InvalidValueError::new(kind, s));
+    }
+    Ok(components.into_iter().map(|c| c.to_owned()).collect())
+}
+
+/// Join components back into a colon-delimited value, the serialize-side
+/// counterpart to `split_colon_components`.  Fails instead of silently
+/// producing an ambiguous round-trip if any component is empty or itself
+/// contains a colon.  `kind` names the value for error messages, as above.
+fn join_colon_components(components: &[&str], kind: &'static str)
+    -> Result<String, InvalidValueError>
+{
+    if components.iter().any(|c| c.is_empty() || c.contains(':')) {
+        return Err(InvalidValueError::new(kind, &components.join(":")));
+    }
+    Ok(components.join(":"))
+}
+
 /// The name of an external resource, and an optional local alias to which
 /// it is mapped inside a container.
 ///
@@ -32,40 +67,45 @@ impl AliasedName {
 
     /// (Internal.) Validate an aliased name is safely serializeable.
     fn validate(&self) -> Result<(), InvalidValueError> {
-        let bad_name = self.name.contains(":");
-        let bad_alias = self.alias.as_ref()
-            .map(|a| a.contains(":")).unwrap_or(false);
-        if bad_name || bad_alias {
-            let val = format!("{:?}", &self);
-            return Err(InvalidValueError::new("aliased name", &val));
-        }
+        try!(self.join());
         Ok(())
     }
+
+    /// (Internal.) Join `name` and `alias` back into a colon-delimited
+    /// string, the same way `from_str` split them apart.
+    fn join(&self) -> Result<String, InvalidValueError> {
+        match self.alias {
+            Some(ref alias) => {
+                join_colon_components(&[&self.name, alias], "aliased name")
+            }
+            None => join_colon_components(&[&self.name], "aliased name"),
+        }
+    }
 }
 
 impl SimpleSerializeDeserialize for AliasedName {
     /// Parse an aliased name from a string.
     fn from_str(s: &str) -> Result<AliasedName, InvalidValueError> {
-        lazy_static! {
-            static ref ALIASED_NAME: Regex =
-                Regex::new("^([^:]+)(?::([^:]+))?$").unwrap();
+        // An aliased name is the two-component specialization of the shared
+        // colon-delimited grammar: `name` with an optional `:alias`.
+        let mut components =
+            try!(split_colon_components(s, "aliased name", 1, 2));
+        let alias = components.pop().unwrap();
+        match components.pop() {
+            Some(name) => Ok(AliasedName {
+                name: name,
+                alias: Some(alias),
+            }),
+            None => Ok(AliasedName {
+                name: alias,
+                alias: None,
+            }),
         }
-        let caps = try!(ALIASED_NAME.captures(s).ok_or_else(|| {
-            InvalidValueError::new("aliased name", s)
-        }));
-        Ok(AliasedName {
-            name: caps.at(1).unwrap().to_owned(),
-            alias: caps.at(2).map(|v| v.to_owned()),
-        })
     }
 
     /// Convert to a string.
     fn to_string(&self) -> Result<String, InvalidValueError> {
-        try!(self.validate());
-        match &self.alias {
-            &Some(ref alias) => Ok(format!("{}:{}", &self.name, alias)),
-            &None => Ok(self.name.to_owned()),
-        }
+        self.join()
     }
 }
 
@@ -86,4 +126,7 @@ fn aliased_name_can_be_converted_to_and_from_a_string() {
                "foo");
     assert_eq!(AliasedName::new("foo", Some("bar")).unwrap().to_string().unwrap(),
                "foo:bar");
+
+    assert!(AliasedName::new("foo:bar", None).is_err());
+    assert!(AliasedName::new("foo", Some("bar:baz")).is_err());
 }
\ No newline at end of file
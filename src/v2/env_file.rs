@@ -1,12 +1,12 @@
 //! Support for parsing the files pointed to by `env_file:`.
 
-use regex::Regex;
 use std::collections::BTreeMap;
 use std::fs;
 use std::io::{self, BufRead};
 use std::path::Path;
 
 use errors::*;
+use super::merge::Merge;
 
 /// A file pointed to by an `env_file:` field.
 pub struct EnvFile {
@@ -14,6 +14,15 @@ pub struct EnvFile {
     vars: BTreeMap<String, String>,
 }
 
+/// Layering two env files follows the same per-key override rule as the rest
+/// of the compose-file merge: variables from the higher-priority file win,
+/// and variables only one side defines are kept.
+impl Merge for EnvFile {
+    fn merge(&mut self, other: EnvFile) {
+        self.vars.merge(other.vars);
+    }
+}
+
 impl EnvFile {
     /// Read an `EnvFile` from a stream.
     pub fn read<R: io::Read>(input: R) -> Result<EnvFile> {
@@ -21,23 +30,9 @@ impl EnvFile {
         let reader = io::BufReader::new(input);
         for line_result in reader.lines() {
             let line = try!(line_result.chain_err(|| "I/O error"));
-
-            lazy_static! {
-                static ref BLANK: Regex =
-                    Regex::new(r#"^\s*(:?#.*)?$"#).unwrap();
-                // We allow lowercase env vars even if POSIX doesn't.
-                static ref VAR:  Regex =
-                    Regex::new(r#"^([_A-Za-z][_A-Za-z0-9]*)=(.*)"#).unwrap();
+            if let Some((key, value)) = try!(parse_line(&line)) {
+                vars.insert(key, value);
             }
-
-            if BLANK.is_match(&line) {
-                continue;
-            }
-
-            let caps = try!(VAR.captures(&line)
-                .ok_or_else(|| ErrorKind::ParseEnv(line.clone())));
-            vars.insert(caps.at(1).unwrap().to_owned(),
-                        caps.at(2).unwrap().to_owned());
         }
         Ok(EnvFile { vars: vars })
     }
@@ -49,6 +44,20 @@ impl EnvFile {
         EnvFile::read(io::BufReader::new(f)).chain_err(&mkerr)
     }
 
+    /// Merge a sequence of env files into one, applying them left to right
+    /// so that later files override earlier ones, mirroring
+    /// `docker-compose --env-file a --env-file b`.
+    pub fn merged<I>(files: I) -> EnvFile
+    where
+        I: IntoIterator<Item = EnvFile>,
+    {
+        let mut merged = EnvFile { vars: BTreeMap::new() };
+        for file in files {
+            merged.merge(file);
+        }
+        merged
+    }
+
     /// The variable mappings as simple BTreeMap.
     pub fn as_map(&self) -> &BTreeMap<String, String> {
         &self.vars
@@ -62,6 +71,129 @@ impl EnvFile {
     // }
 }
 
+/// Is `c` a legal first character of an env-var name?  We allow lowercase
+/// names even though POSIX doesn't.
+fn is_name_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic()
+}
+
+/// Parse a single line of an `.env` file, returning `Some((key, value))` for
+/// an assignment or `None` for a blank or comment-only line.
+///
+/// We follow the rules used by Docker and the popular dotenv tooling: an
+/// optional leading `export ` is ignored, surrounding single or double quotes
+/// are stripped, and an unquoted trailing ` # comment` is dropped.
+fn parse_line(line: &str) -> Result<Option<(String, String)>> {
+    let trimmed = line.trim_start();
+
+    // Blank lines and whole-line comments carry no variable.
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Ok(None);
+    }
+
+    // An optional `export ` prefix is allowed for files that double as shell
+    // scripts.
+    let trimmed = if trimmed.starts_with("export ") {
+        trimmed["export ".len()..].trim_start()
+    } else {
+        trimmed
+    };
+
+    let eq = try!(trimmed.find('=')
+        .ok_or_else(|| ErrorKind::ParseEnv(line.to_owned())));
+    let name = trimmed[..eq].trim_end();
+    if name.is_empty() || !is_name_start(name.chars().next().unwrap())
+        || !name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+    {
+        return Err(ErrorKind::ParseEnv(line.to_owned()).into());
+    }
+
+    let value = try!(parse_value(&trimmed[eq + 1..], line));
+    Ok(Some((name.to_owned(), value)))
+}
+
+/// Parse the right-hand side of a `KEY=value` assignment with a small state
+/// machine.  Single-quoted values are taken literally, double-quoted values
+/// honor `\n`, `\t` and `\\` escapes, and unquoted values are trimmed and may
+/// carry a trailing ` # comment`.
+///
+/// For a quoted value, anything after the closing quote must be whitespace
+/// or a `#` comment; other trailing text (e.g. `FOO="bar"baz`) is a parse
+/// error rather than silently discarded data.
+fn parse_value(raw: &str, line: &str) -> Result<String> {
+    let mut chars = raw.trim_start().chars().peekable();
+    match chars.peek().cloned() {
+        // A single-quoted value is literal up to the closing quote.
+        Some('\'') => {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some(c) => value.push(c),
+                    None => {
+                        return Err(ErrorKind::ParseEnv(line.to_owned()).into())
+                    }
+                }
+            }
+            try!(check_no_trailing_text(&chars.collect::<String>(), line));
+            Ok(value)
+        }
+        // A double-quoted value processes a handful of escape sequences.
+        Some('"') => {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some('\\') => value.push('\\'),
+                        Some('"') => value.push('"'),
+                        Some(other) => {
+                            value.push('\\');
+                            value.push(other);
+                        }
+                        None => {
+                            return Err(
+                                ErrorKind::ParseEnv(line.to_owned()).into(),
+                            )
+                        }
+                    },
+                    Some(c) => value.push(c),
+                    None => {
+                        return Err(ErrorKind::ParseEnv(line.to_owned()).into())
+                    }
+                }
+            }
+            try!(check_no_trailing_text(&chars.collect::<String>(), line));
+            Ok(value)
+        }
+        // An unquoted value runs until an inline comment and is trimmed.
+        _ => {
+            let rest: String = chars.collect();
+            let value = match rest.find(" #") {
+                Some(idx) => &rest[..idx],
+                None => &rest,
+            };
+            Ok(value.trim().to_owned())
+        }
+    }
+}
+
+/// Reject anything following a quoted value's closing quote other than
+/// whitespace or a `#` comment, so that malformed lines like `FOO="bar"baz`
+/// are caught instead of having `baz` silently dropped.
+fn check_no_trailing_text(trailing: &str, line: &str) -> Result<()> {
+    let trailing = trailing.trim_start();
+    if trailing.is_empty() || trailing.starts_with('#') {
+        Ok(())
+    } else {
+        Err(ErrorKind::ParseEnv(line.to_owned()).into())
+    }
+}
+
 #[test]
 fn parses_docker_compatible_env_files() {
     let input = r#"
@@ -72,15 +204,62 @@ fn parses_docker_compatible_env_files() {
 FOO=foo
 BAR=2
 
-# Docker does not currently do anything special with quotes!
+# Surrounding quotes are stripped.
 WEIRD="quoted"
-
-# TODO LOW: What if an .env file contains a shell variable interpolation?
 "#;
     let cursor = io::Cursor::new(input);
     let env_file = EnvFile::read(cursor).unwrap();
     let env = env_file.as_map();
     assert_eq!(env.get("FOO").unwrap(), "foo");
     assert_eq!(env.get("BAR").unwrap(), "2");
-    assert_eq!(env.get("WEIRD").unwrap(), "\"quoted\"");
+    assert_eq!(env.get("WEIRD").unwrap(), "quoted");
+}
+
+#[test]
+fn merges_later_files_over_earlier_ones() {
+    let base = EnvFile::read(io::Cursor::new("A=1\nB=2\n")).unwrap();
+    let over = EnvFile::read(io::Cursor::new("B=20\nC=3\n")).unwrap();
+    let merged = EnvFile::merged(vec![base, over]);
+    let env = merged.as_map();
+    assert_eq!(env.get("A").unwrap(), "1");
+    assert_eq!(env.get("B").unwrap(), "20");
+    assert_eq!(env.get("C").unwrap(), "3");
+}
+
+#[test]
+fn handles_quoting_escapes_export_and_comments() {
+    let input = r#"
+export EXPORTED=value
+SINGLE='literal $NOT #expanded'
+DOUBLE="line1\nline2\ttabbed"
+SPACED="has spaces"
+HASH_IN_QUOTES="a # b"
+TRAILING=bare # this is a comment
+EMPTY=
+EMPTY_QUOTED=""
+"#;
+    let cursor = io::Cursor::new(input);
+    let env_file = EnvFile::read(cursor).unwrap();
+    let env = env_file.as_map();
+    assert_eq!(env.get("EXPORTED").unwrap(), "value");
+    assert_eq!(env.get("SINGLE").unwrap(), "literal $NOT #expanded");
+    assert_eq!(env.get("DOUBLE").unwrap(), "line1\nline2\ttabbed");
+    assert_eq!(env.get("SPACED").unwrap(), "has spaces");
+    assert_eq!(env.get("HASH_IN_QUOTES").unwrap(), "a # b");
+    assert_eq!(env.get("TRAILING").unwrap(), "bare");
+    assert_eq!(env.get("EMPTY").unwrap(), "");
+    assert_eq!(env.get("EMPTY_QUOTED").unwrap(), "");
+}
+
+#[test]
+fn rejects_text_after_a_quoted_value_closing_quote() {
+    let cursor = io::Cursor::new("FOO=\"bar\"baz\n");
+    assert!(EnvFile::read(cursor).is_err());
+}
+
+#[test]
+fn allows_a_comment_after_a_quoted_value_closing_quote() {
+    let cursor = io::Cursor::new("FOO=\"bar\" # comment\n");
+    let env_file = EnvFile::read(cursor).unwrap();
+    assert_eq!(env_file.as_map().get("FOO").unwrap(), "bar");
 }
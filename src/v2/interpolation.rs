@@ -0,0 +1,251 @@
+//! A scanner for `docker-compose`-style `$VAR` interpolation.
+//!
+//! This is the low-level half of the feature: given a raw string and an
+//! already-assembled `name -> value` map, [`interpolate`](fn.interpolate.html)
+//! walks the string and resolves references according to the grammar
+//! described on that function.  Where the map comes from (an `EnvFile`, the
+//! process environment, or some combination) is up to the caller; see
+//! [`RawOr::interpolate`](../raw_or/struct.RawOr.html#method.interpolate)
+//! for how this is plugged into a parsed field.
+
+use std::collections::BTreeMap;
+
+use errors::*;
+
+/// Is `c` a legal first character of a variable name?
+fn is_name_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic()
+}
+
+/// Is `c` a legal non-initial character of a variable name?
+fn is_name_continue(c: char) -> bool {
+    c == '_' || c.is_ascii_alphanumeric()
+}
+
+/// Interpolate `docker-compose`-style variable references in `raw`, drawing
+/// values from `env`.  We walk the string left to right and honor the
+/// following forms:
+///
+/// * `$$` emits a single literal `$`.
+/// * `$NAME` and `${NAME}` substitute the value of `NAME`, or the empty
+///   string if `NAME` is unset.
+/// * `${NAME:-default}` substitutes `default` when `NAME` is unset or empty,
+///   while `${NAME-default}` does so only when `NAME` is unset.
+/// * `${NAME:?msg}` / `${NAME?msg}` fail with an
+///   [`ErrorKind::InterpolateUndefined`] carrying `msg` when `NAME` is unset
+///   (or empty, for the `:?` form).
+///
+/// A lone `$` that does not form a valid reference is a hard error, so that
+/// typos can't slip through unsubstituted.
+pub fn interpolate(
+    raw: &str,
+    env: &BTreeMap<String, String>,
+) -> Result<String> {
+    let mut out = String::with_capacity(raw.len());
+    // We drive a small hand-written scanner over the characters, tracking
+    // byte offsets so that braced references can be sliced out cheaply.
+    let bytes = raw.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        if c != '$' {
+            // Copy the character across verbatim.  We index back into the
+            // original `&str` to stay UTF-8 correct for multibyte runs.
+            let start = i;
+            i += 1;
+            while i < bytes.len() && bytes[i] & 0xC0 == 0x80 {
+                i += 1;
+            }
+            out.push_str(&raw[start..i]);
+            continue;
+        }
+
+        // We're looking at a `$`.  Figure out what follows it.
+        i += 1;
+        match bytes.get(i).map(|&b| b as char) {
+            // `$$` is an escaped dollar sign.
+            Some('$') => {
+                out.push('$');
+                i += 1;
+            }
+            // `${...}` is a braced reference, possibly with a modifier.
+            Some('{') => {
+                i += 1;
+                let name_start = i;
+                while i < bytes.len() && is_name_continue(bytes[i] as char) {
+                    i += 1;
+                }
+                let name = &raw[name_start..i];
+                if name.is_empty() || !is_name_start(name.as_bytes()[0] as char) {
+                    return Err(ErrorKind::InterpolateInvalidSyntax(
+                        raw.to_owned(),
+                    ).into());
+                }
+
+                // The remainder is either the closing brace or a modifier
+                // (`:-`, `-`, `:?`, `?`) followed by its argument.
+                let (modifier, arg, consumed) = try!(
+                    try_parse_modifier(&raw[i..]).ok_or_else(|| {
+                        ErrorKind::InterpolateInvalidSyntax(raw.to_owned())
+                    })
+                );
+                i += consumed;
+
+                out.push_str(&try!(apply_modifier(name, modifier, arg, env)));
+            }
+            // `$NAME` is an unbraced reference.
+            Some(c) if is_name_start(c) => {
+                let name_start = i;
+                i += 1;
+                while i < bytes.len() && is_name_continue(bytes[i] as char) {
+                    i += 1;
+                }
+                let name = &raw[name_start..i];
+                out.push_str(env.get(name).map(String::as_str).unwrap_or(""));
+            }
+            // A lone `$` is a typo, not a value.
+            _ => {
+                return Err(ErrorKind::InterpolateInvalidSyntax(
+                    raw.to_owned(),
+                ).into())
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The kind of modifier found inside a `${...}` reference.
+#[derive(Clone, Copy)]
+enum Modifier {
+    /// No modifier: `${NAME}`.
+    None,
+    /// `${NAME:-default}` — default when unset or empty.
+    DefaultWhenEmpty,
+    /// `${NAME-default}` — default when unset only.
+    DefaultWhenUnset,
+    /// `${NAME:?msg}` — error when unset or empty.
+    ErrorWhenEmpty,
+    /// `${NAME?msg}` — error when unset only.
+    ErrorWhenUnset,
+}
+
+/// Parse the portion of a braced reference following the variable name,
+/// returning the modifier, its argument, and the number of bytes consumed
+/// (including the closing brace).  Returns `None` if the reference is
+/// malformed (e.g. missing closing brace).
+fn try_parse_modifier(rest: &str) -> Option<(Modifier, &str, usize)> {
+    let bytes = rest.as_bytes();
+    let (modifier, arg_start) = match bytes.first().map(|&b| b as char) {
+        Some('}') => return Some((Modifier::None, "", 1)),
+        Some(':') => match bytes.get(1).map(|&b| b as char) {
+            Some('-') => (Modifier::DefaultWhenEmpty, 2),
+            Some('?') => (Modifier::ErrorWhenEmpty, 2),
+            _ => return None,
+        },
+        Some('-') => (Modifier::DefaultWhenUnset, 1),
+        Some('?') => (Modifier::ErrorWhenUnset, 1),
+        _ => return None,
+    };
+    let close = match rest[arg_start..].find('}') {
+        Some(close) => close,
+        None => return None,
+    };
+    Some((modifier, &rest[arg_start..arg_start + close], arg_start + close + 1))
+}
+
+/// Resolve a single braced reference given its name, modifier and argument.
+fn apply_modifier(
+    name: &str,
+    modifier: Modifier,
+    arg: &str,
+    env: &BTreeMap<String, String>,
+) -> Result<String> {
+    let value = env.get(name);
+    let is_empty = value.map(|v| v.is_empty()).unwrap_or(true);
+    match modifier {
+        Modifier::None => Ok(value.cloned().unwrap_or_default()),
+        Modifier::DefaultWhenEmpty if is_empty => Ok(arg.to_owned()),
+        Modifier::DefaultWhenUnset if value.is_none() => Ok(arg.to_owned()),
+        Modifier::DefaultWhenEmpty | Modifier::DefaultWhenUnset => {
+            Ok(value.cloned().unwrap_or_default())
+        }
+        Modifier::ErrorWhenEmpty if is_empty => {
+            Err(ErrorKind::InterpolateUndefined(
+                name.to_owned(),
+                arg.to_owned(),
+            ).into())
+        }
+        Modifier::ErrorWhenUnset if value.is_none() => {
+            Err(ErrorKind::InterpolateUndefined(
+                name.to_owned(),
+                arg.to_owned(),
+            ).into())
+        }
+        Modifier::ErrorWhenEmpty | Modifier::ErrorWhenUnset => {
+            Ok(value.cloned().unwrap_or_default())
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_env() -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    env.insert("FOO".to_owned(), "foo".to_owned());
+    env.insert("EMPTY".to_owned(), "".to_owned());
+    env
+}
+
+#[test]
+fn substitutes_plain_and_braced_references() {
+    let env = test_env();
+    assert_eq!(interpolate("$FOO", &env).unwrap(), "foo");
+    assert_eq!(interpolate("${FOO}", &env).unwrap(), "foo");
+    assert_eq!(interpolate("a/${FOO}/b", &env).unwrap(), "a/foo/b");
+    assert_eq!(interpolate("$UNSET", &env).unwrap(), "");
+    assert_eq!(interpolate("${UNSET}", &env).unwrap(), "");
+}
+
+#[test]
+fn escapes_double_dollar() {
+    let env = test_env();
+    assert_eq!(interpolate("$$FOO", &env).unwrap(), "$FOO");
+    assert_eq!(interpolate("price: $$5", &env).unwrap(), "price: $5");
+}
+
+#[test]
+fn handles_default_modifiers() {
+    let env = test_env();
+    // `:-` falls back when unset or empty.
+    assert_eq!(interpolate("${UNSET:-def}", &env).unwrap(), "def");
+    assert_eq!(interpolate("${EMPTY:-def}", &env).unwrap(), "def");
+    assert_eq!(interpolate("${FOO:-def}", &env).unwrap(), "foo");
+    // `-` falls back only when unset.
+    assert_eq!(interpolate("${UNSET-def}", &env).unwrap(), "def");
+    assert_eq!(interpolate("${EMPTY-def}", &env).unwrap(), "");
+}
+
+#[test]
+fn handles_error_modifiers() {
+    let env = test_env();
+    assert_eq!(interpolate("${FOO:?nope}", &env).unwrap(), "foo");
+    match *interpolate("${EMPTY:?must be set}", &env).unwrap_err().kind() {
+        ErrorKind::InterpolateUndefined(ref name, ref msg) => {
+            assert_eq!(name, "EMPTY");
+            assert_eq!(msg, "must be set");
+        }
+        ref e => panic!("unexpected error: {}", e),
+    }
+    // `?` (without the colon) fires only when unset, not when empty.
+    assert_eq!(interpolate("${EMPTY?nope}", &env).unwrap(), "");
+    assert!(interpolate("${UNSET?required}", &env).is_err());
+}
+
+#[test]
+fn rejects_invalid_syntax() {
+    let env = test_env();
+    assert!(interpolate("$", &env).is_err());
+    assert!(interpolate("foo $ bar", &env).is_err());
+    assert!(interpolate("${}", &env).is_err());
+    assert!(interpolate("${FOO", &env).is_err());
+    assert!(interpolate("${1BAD}", &env).is_err());
+}
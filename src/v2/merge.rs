@@ -0,0 +1,171 @@
+//! The merge policies `docker-compose -f a.yml -f b.yml` applies when
+//! layering compose files: scalars and single-valued list fields
+//! (`command`, `entrypoint`) are replaced outright by the higher-priority
+//! file, maps (`services`, `environment`, `labels`, `volumes`, `networks`)
+//! are deep-merged key by key, and multiset sequence fields (`ports`,
+//! `expose`, `volumes`, `dns`) are concatenated rather than replaced.
+//!
+//! **Status:** `File::merge`/`File::merged` — the entry point this was
+//! meant to back — is still unimplemented. Neither `File` nor `Service`
+//! exist in this checkout (`mod.rs` expects `file.in.rs`/`service.in.rs`,
+//! which aren't present), so there's no struct to apply these policies to
+//! field by field yet. `Merge`, `replace` and `concat` below are the
+//! reusable primitives a future `File`/`Service::merge` would call once
+//! those types land — the same way `string_or_struct`'s helpers are public
+//! ahead of a field to attach them to. [`EnvFile`](../env_file/struct.EnvFile.html)
+//! is the one concrete type merged through this substrate today, and it
+//! only exercises the map case.
+
+use std::collections::BTreeMap;
+
+/// A value that knows how to absorb a higher-priority value layered on top
+/// of it, following the compose override semantics.
+pub trait Merge {
+    /// Merge `other` (the higher-priority value) into `self`.
+    fn merge(&mut self, other: Self);
+}
+
+/// Implement `Merge` for a scalar type by overwriting `self` with `other`
+/// outright.  Plugging `String` in here is what gives the `BTreeMap` impl
+/// below its per-key "later wins" behavior: merging two string-valued maps
+/// recurses down to this impl for any key present on both sides.
+macro_rules! merge_by_replacement {
+    ( $( $ty:ty ),* ) => {
+        $(
+            impl Merge for $ty {
+                fn merge(&mut self, other: $ty) {
+                    *self = other;
+                }
+            }
+        )*
+    };
+}
+
+merge_by_replacement!(String, bool, u16, i64, ::std::path::PathBuf);
+
+/// An optional field is merged by recursing when both sides are present, and
+/// otherwise taking whichever side is present (preferring `other`).  This
+/// gives deep-merge behavior for map-valued options while still letting an
+/// override supply a value that the base omitted.
+impl<T: Merge> Merge for Option<T> {
+    fn merge(&mut self, other: Option<T>) {
+        match (self.as_mut(), other) {
+            (Some(ours), Some(theirs)) => ours.merge(theirs),
+            (None, theirs @ Some(_)) => *self = theirs,
+            (_, None) => {}
+        }
+    }
+}
+
+/// Maps are deep-merged key by key: keys present only on one side are kept,
+/// and keys present on both are merged recursively.  This is the policy for
+/// `services`, `environment`, `labels`, `volumes` and `networks`.  For the
+/// string-valued maps (`environment`, `labels`) the value type's replacement
+/// policy means a shared key is overridden wholesale, exactly as
+/// `docker-compose` does.
+impl<V: Merge> Merge for BTreeMap<String, V> {
+    fn merge(&mut self, other: BTreeMap<String, V>) {
+        for (key, value) in other {
+            match self.get_mut(&key) {
+                Some(existing) => existing.merge(value),
+                None => {
+                    self.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+/// Replace a scalar or single-valued-list field with the higher-priority
+/// value.  This is the policy for plain scalars and for list-valued fields
+/// like `command` and `entrypoint`, which `docker-compose` replaces
+/// wholesale rather than merging element by element.
+pub fn replace<T>(ours: &mut T, theirs: T) {
+    *ours = theirs;
+}
+
+/// Concatenate a multiset sequence field, appending the higher-priority
+/// file's entries after the base's rather than replacing them.  This is the
+/// policy for fields like `ports`, `expose`, `volumes` and `dns`.
+pub fn concat<T>(ours: &mut Vec<T>, mut theirs: Vec<T>) {
+    ours.append(&mut theirs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A trivial leaf type whose merge policy is "replace", used to exercise
+    // the generic map and option impls.
+    #[derive(Debug, PartialEq)]
+    struct Scalar(i32);
+
+    impl Merge for Scalar {
+        fn merge(&mut self, other: Scalar) {
+            *self = other;
+        }
+    }
+
+    #[test]
+    fn maps_are_deep_merged_key_by_key() {
+        let mut base: BTreeMap<String, Scalar> = BTreeMap::new();
+        base.insert("a".to_owned(), Scalar(1));
+        base.insert("b".to_owned(), Scalar(2));
+
+        let mut over: BTreeMap<String, Scalar> = BTreeMap::new();
+        over.insert("b".to_owned(), Scalar(20));
+        over.insert("c".to_owned(), Scalar(3));
+
+        base.merge(over);
+        assert_eq!(base.get("a"), Some(&Scalar(1)));
+        assert_eq!(base.get("b"), Some(&Scalar(20)));
+        assert_eq!(base.get("c"), Some(&Scalar(3)));
+    }
+
+    #[test]
+    fn options_prefer_the_override_but_fill_gaps() {
+        let mut present = Some(Scalar(1));
+        present.merge(Some(Scalar(2)));
+        assert_eq!(present, Some(Scalar(2)));
+
+        let mut missing: Option<Scalar> = None;
+        missing.merge(Some(Scalar(5)));
+        assert_eq!(missing, Some(Scalar(5)));
+
+        let mut keep = Some(Scalar(9));
+        keep.merge(None);
+        assert_eq!(keep, Some(Scalar(9)));
+    }
+
+    #[test]
+    fn string_valued_maps_override_per_key() {
+        // This is the `environment`/`labels` policy: a shared key is taken
+        // from the higher-priority map, other keys are unioned.
+        let mut base: BTreeMap<String, String> = BTreeMap::new();
+        base.insert("A".to_owned(), "1".to_owned());
+        base.insert("B".to_owned(), "2".to_owned());
+
+        let mut over: BTreeMap<String, String> = BTreeMap::new();
+        over.insert("B".to_owned(), "20".to_owned());
+        over.insert("C".to_owned(), "3".to_owned());
+
+        base.merge(over);
+        assert_eq!(base.get("A").unwrap(), "1");
+        assert_eq!(base.get("B").unwrap(), "20");
+        assert_eq!(base.get("C").unwrap(), "3");
+    }
+
+    #[test]
+    fn replace_overwrites_with_the_higher_priority_value() {
+        let mut command = vec!["old".to_owned()];
+        replace(&mut command, vec!["new".to_owned(), "args".to_owned()]);
+        assert_eq!(command, vec!["new".to_owned(), "args".to_owned()]);
+    }
+
+    #[test]
+    fn multiset_sequences_are_concatenated() {
+        let mut ports = vec!["80", "443"];
+        concat(&mut ports, vec!["8080"]);
+        assert_eq!(ports, vec!["80", "443", "8080"]);
+    }
+}
@@ -9,8 +9,14 @@ use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use self::helpers::*;
+pub use self::interpolation::interpolate;
+pub use self::merge::{concat, replace, Merge};
+pub use self::raw_or::{raw, value, RawOr};
 
 mod helpers;
+mod interpolation;
+mod merge;
+mod raw_or;
 
 macro_rules! assert_roundtrip {
     ( $ty:ty, $yaml:expr ) => {
@@ -0,0 +1,79 @@
+//! A wrapper for values that may contain unresolved `$VAR` interpolations.
+
+use std::collections::BTreeMap;
+
+use errors::*;
+use super::interpolation::interpolate;
+
+/// A value that may either be given directly, or written as a raw string
+/// containing `docker-compose`-style `$VAR` interpolations that have not yet
+/// been resolved.
+///
+/// We always keep the original raw form around so that a `File` can be
+/// re-serialized exactly as it was written, even after it has been rendered
+/// against an environment.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawOr<T> {
+    /// The original string as written, preserved for re-serialization when
+    /// the value came from a raw, possibly-interpolated string.
+    raw: Option<String>,
+    /// The value itself.  For a raw string this is the text prior to
+    /// interpolation.
+    value: T,
+}
+
+/// Construct a `RawOr` wrapping a value that was given directly and needs no
+/// interpolation.
+pub fn value<T>(v: T) -> RawOr<T> {
+    RawOr { raw: None, value: v }
+}
+
+/// Construct a `RawOr<String>` from a raw, possibly-interpolated string,
+/// preserving the original text.
+pub fn raw<S: Into<String>>(s: S) -> RawOr<String> {
+    let s = s.into();
+    RawOr { raw: Some(s.clone()), value: s }
+}
+
+impl RawOr<String> {
+    /// The raw string as originally written, if this value came from one.
+    pub fn to_raw(&self) -> Option<&str> {
+        self.raw.as_ref().map(String::as_str)
+    }
+
+    /// Render this value against `env`, resolving any `$VAR` interpolations
+    /// in the raw form.  The raw form is left untouched, so the original text
+    /// is still available for re-serialization.
+    pub fn interpolate(&self, env: &BTreeMap<String, String>) -> Result<String> {
+        match self.raw {
+            Some(ref raw) => interpolate(raw, env),
+            None => Ok(self.value.clone()),
+        }
+    }
+}
+
+#[test]
+fn renders_raw_or_values_against_an_environment() {
+    let mut env = BTreeMap::new();
+    env.insert("TAG".to_owned(), "v2".to_owned());
+
+    // A directly-given value renders to itself.
+    let direct = value("app:latest".to_owned());
+    assert_eq!(direct.interpolate(&env).unwrap(), "app:latest");
+    assert_eq!(direct.to_raw(), None);
+
+    // A raw value is interpolated, but preserves its original text.  This
+    // stands in for the `RawOr<String>` fields of a parsed `File` (e.g.
+    // `Build::dockerfile`).
+    let mut fields: BTreeMap<String, RawOr<String>> = BTreeMap::new();
+    fields.insert("IMAGE".to_owned(), raw("app:${TAG}"));
+    fields.insert("HOME".to_owned(), raw("${MISSING:-/root}"));
+
+    let image = fields.get("IMAGE").unwrap();
+    assert_eq!(image.interpolate(&env).unwrap(), "app:v2");
+    assert_eq!(image.to_raw(), Some("app:${TAG}"));
+    assert_eq!(
+        fields.get("HOME").unwrap().interpolate(&env).unwrap(),
+        "/root"
+    );
+}
@@ -60,6 +60,88 @@ where
     d.deserialize_any(StringOrStruct(PhantomData))
 }
 
+/// Like `deserialize_string_or_struct`, but also accepts a bare sequence:
+/// a string is turned into a `T` using `FromStr::from_str`, while a
+/// sequence or a map is forwarded to `T`'s own `Deserialize`
+/// implementation.  The `Visitor` below just adds a `visit_seq` arm next to
+/// `deserialize_string_or_struct`'s `visit_str`/`visit_map` pair.
+pub fn deserialize_string_or_seq_or_struct<'de, T, D>(d: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de> + FromStr,
+    <T as FromStr>::Err: Display,
+    D: Deserializer<'de>,
+{
+    /// Declare an internal visitor type to handle our input.
+    struct StringOrSeqOrStruct<T>(PhantomData<T>);
+
+    impl<'de, T> de::Visitor<'de> for StringOrSeqOrStruct<T>
+    where
+        T: Deserialize<'de> + FromStr,
+        <T as FromStr>::Err: Display,
+    {
+        type Value = T;
+
+        fn visit_str<E>(self, value: &str) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            FromStr::from_str(value).map_err(|err| {
+                // Just convert the underlying error type into a string and
+                // pass it to serde as a custom error.
+                de::Error::custom(format!("{}", err))
+            })
+        }
+
+        fn visit_seq<A>(self, visitor: A) -> Result<T, A::Error>
+        where
+            A: de::SeqAccess<'de>,
+        {
+            let svd = de::value::SeqAccessDeserializer::new(visitor);
+            Deserialize::deserialize(svd)
+        }
+
+        fn visit_map<M>(self, visitor: M) -> Result<T, M::Error>
+        where
+            M: de::MapAccess<'de>,
+        {
+            let mvd = de::value::MapAccessDeserializer::new(visitor);
+            Deserialize::deserialize(mvd)
+        }
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(formatter, "a string, a sequence or a map")
+        }
+    }
+
+    d.deserialize_any(StringOrSeqOrStruct(PhantomData))
+}
+
+/// Some types can be serialized as a bare string under certain
+/// circumstances, and otherwise fall back to a sequence or a map.
+pub trait SerializeStringOrSeqOrStruct: Serialize {
+    /// Serialize either a string representation of this value, or its full
+    /// sequence/map form if it cannot be represented as a string.
+    fn serialize_string_or_seq_or_struct<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+}
+
+/// Serialize the specified value as a string if we can, and as a sequence or
+/// map otherwise.
+pub fn serialize_string_or_seq_or_struct<T, S>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: SerializeStringOrSeqOrStruct,
+    S: Serializer,
+{
+    value.serialize_string_or_seq_or_struct(serializer)
+}
+
 /// Like `opt_string_or_struct`, but it also handles the case where the
 /// value is optional.
 ///
@@ -165,3 +247,141 @@ where
         Some(ref v) => serializer.serialize_some(&Wrap(v)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml;
+    use void::Void;
+
+    /// A stand-in for a field like `environment`, which can be written as a
+    /// bare `"KEY=VALUE,..."` string, a sequence of `"KEY=VALUE"` entries, or
+    /// a YAML map.  Used only to exercise every branch of
+    /// `deserialize_string_or_seq_or_struct` end to end.
+    #[derive(Debug, PartialEq)]
+    struct EnvList(Vec<String>);
+
+    impl FromStr for EnvList {
+        type Err = Void;
+
+        fn from_str(s: &str) -> Result<EnvList, Void> {
+            if s.is_empty() {
+                Ok(EnvList(vec![]))
+            } else {
+                Ok(EnvList(s.split(',').map(|p| p.to_owned()).collect()))
+            }
+        }
+    }
+
+    struct EnvListVisitor;
+
+    impl<'de> de::Visitor<'de> for EnvListVisitor {
+        type Value = EnvList;
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<EnvList, A::Error>
+        where
+            A: de::SeqAccess<'de>,
+        {
+            let mut pairs = Vec::new();
+            while let Some(item) = try!(seq.next_element::<String>()) {
+                pairs.push(item);
+            }
+            Ok(EnvList(pairs))
+        }
+
+        fn visit_map<M>(self, mut map: M) -> Result<EnvList, M::Error>
+        where
+            M: de::MapAccess<'de>,
+        {
+            let mut pairs = Vec::new();
+            while let Some((key, value)) =
+                try!(map.next_entry::<String, String>())
+            {
+                pairs.push(format!("{}={}", key, value));
+            }
+            Ok(EnvList(pairs))
+        }
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(formatter, "a sequence or a map")
+        }
+    }
+
+    impl<'de> Deserialize<'de> for EnvList {
+        fn deserialize<D>(deserializer: D) -> Result<EnvList, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(EnvListVisitor)
+        }
+    }
+
+    impl Serialize for EnvList {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            self.0.serialize(serializer)
+        }
+    }
+
+    impl SerializeStringOrSeqOrStruct for EnvList {
+        fn serialize_string_or_seq_or_struct<S>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            self.0.serialize(serializer)
+        }
+    }
+
+    /// A single-field wrapper, the way a real `Service` field would call
+    /// these helpers from its own `Deserialize`/`Serialize` impls.
+    #[derive(Debug, PartialEq)]
+    struct Wrapper(EnvList);
+
+    impl<'de> Deserialize<'de> for Wrapper {
+        fn deserialize<D>(deserializer: D) -> Result<Wrapper, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserialize_string_or_seq_or_struct(deserializer).map(Wrapper)
+        }
+    }
+
+    impl Serialize for Wrapper {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serialize_string_or_seq_or_struct(&self.0, serializer)
+        }
+    }
+
+    #[test]
+    fn deserializes_from_a_bare_string() {
+        let w: Wrapper = serde_yaml::from_str(r#""A=1,B=2""#).unwrap();
+        assert_eq!(w, Wrapper(EnvList(vec!["A=1".to_owned(), "B=2".to_owned()])));
+    }
+
+    #[test]
+    fn deserializes_from_a_sequence() {
+        let w: Wrapper = serde_yaml::from_str(r#"["A=1", "B=2"]"#).unwrap();
+        assert_eq!(w, Wrapper(EnvList(vec!["A=1".to_owned(), "B=2".to_owned()])));
+    }
+
+    #[test]
+    fn deserializes_from_a_map() {
+        let w: Wrapper = serde_yaml::from_str("A: \"1\"\nB: \"2\"\n").unwrap();
+        assert_eq!(w, Wrapper(EnvList(vec!["A=1".to_owned(), "B=2".to_owned()])));
+    }
+
+    #[test]
+    fn serializes_the_underlying_value() {
+        let w = Wrapper(EnvList(vec!["A=1".to_owned()]));
+        let yaml = serde_yaml::to_string(&w).unwrap();
+        assert_eq!(yaml.trim(), "- A=1");
+    }
+}